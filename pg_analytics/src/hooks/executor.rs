@@ -3,8 +3,12 @@ use deltalake::datafusion::error::DataFusionError;
 use deltalake::datafusion::logical_expr::LogicalPlan;
 use deltalake::datafusion::sql::parser::DFParser;
 use deltalake::datafusion::sql::planner::SqlToRel;
+use deltalake::datafusion::sql::sqlparser::ast::Statement as SqlStatement;
 use deltalake::datafusion::sql::sqlparser::dialect::PostgreSqlDialect;
+use pgrx::spi::Spi;
 use pgrx::*;
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::ffi::CStr;
 
 use crate::datafusion::commit::{commit_writer, needs_commit};
@@ -16,6 +20,38 @@ use crate::hooks::insert::insert;
 use crate::hooks::query::Query;
 use crate::hooks::select::select;
 
+thread_local! {
+    // Set around the nested `executor_run` that `update`'s staged-insert `Spi::run` triggers,
+    // so that re-entrant call doesn't commit the writer on our behalf. Without this, the
+    // commit-on-entry check below would commit the preceding `delete` alone, before the insert
+    // half of the UPDATE has even run, defeating the single commit `update` issues once both
+    // halves have succeeded.
+    static SKIP_AUTO_COMMIT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Sets `SKIP_AUTO_COMMIT` for the lifetime of this guard and restores the prior value on
+/// drop. Restoring via `Drop` rather than a plain `set(false)` after the guarded call means
+/// the flag still gets unwound if that call exits through a Postgres `elog(ERROR)`-style
+/// longjmp instead of returning normally -- a bare `set`/`set` pair would leave
+/// `SKIP_AUTO_COMMIT` stuck at `true` for the rest of the backend's life in that case.
+struct SkipAutoCommitGuard {
+    previous: bool,
+}
+
+impl SkipAutoCommitGuard {
+    fn new() -> Self {
+        let previous = SKIP_AUTO_COMMIT.with(Cell::get);
+        SKIP_AUTO_COMMIT.with(|flag| flag.set(true));
+        Self { previous }
+    }
+}
+
+impl Drop for SkipAutoCommitGuard {
+    fn drop(&mut self) {
+        SKIP_AUTO_COMMIT.with(|flag| flag.set(self.previous));
+    }
+}
+
 pub fn executor_run(
     query_desc: PgBox<pg_sys::QueryDesc>,
     direction: pg_sys::ScanDirection,
@@ -28,7 +64,7 @@ pub fn executor_run(
         execute_once: bool,
     ) -> HookResult<()>,
 ) -> Result<(), ParadeError> {
-    if needs_commit()? {
+    if !SKIP_AUTO_COMMIT.with(Cell::get) && needs_commit()? {
         task::block_on(commit_writer())?;
     }
 
@@ -69,7 +105,7 @@ pub fn executor_run(
         match query_desc.operation {
             pg_sys::CmdType_CMD_DELETE => delete(rtable, query_desc, logical_plan),
             pg_sys::CmdType_CMD_SELECT => select(query_desc, logical_plan),
-            pg_sys::CmdType_CMD_UPDATE => Err(NotSupported::Update.into()),
+            pg_sys::CmdType_CMD_UPDATE => update(rtable, query_desc, &query, logical_plan),
             _ => {
                 prev_hook(query_desc, direction, count, execute_once);
                 Ok(())
@@ -78,6 +114,142 @@ pub fn executor_run(
     }
 }
 
+// Implements UPDATE by lowering it into a delete of the matching rows followed by an
+// insert of the recomputed rows, rather than planning it as its own write path. DataFusion's
+// `logical_plan` for the statement is shaped for a projection/scan, not a delete, so we
+// can't hand it to `delete` directly: instead we re-derive the equivalent
+// `DELETE FROM <table> WHERE <selection>` text from the parsed UPDATE AST and plan that
+// separately.
+//
+// The recomputed rows are staged into a plain (non-deltalake) temp table *before* anything
+// deltalake-side is touched: each assigned column projects its SET expression and every
+// other column passes its current value through unchanged, so the staging table is a frozen
+// snapshot of the post-UPDATE rows. Only once that snapshot exists do we delete the
+// originally matching rows and insert the staged snapshot back in. This ordering means a
+// failure staging the snapshot never touches the table at all, and a failure inserting the
+// snapshot leaves it in place (the `DROP TABLE` below only runs once the insert has
+// succeeded) rather than silently discarding it -- so the rows a failed UPDATE would have
+// produced aren't lost even though the delete they were meant to replace already landed in
+// the writer's pending state.
+fn update(
+    rtable: *mut pg_sys::List,
+    query_desc: PgBox<pg_sys::QueryDesc>,
+    query: &str,
+    _logical_plan: LogicalPlan,
+) -> Result<(), ParadeError> {
+    let dialect = PostgreSqlDialect {};
+    let ast = DFParser::parse_sql_with_dialect(query, &dialect)
+        .map_err(|err| ParadeError::DataFusion(DataFusionError::SQL(err, None)))?;
+
+    let deltalake::datafusion::sql::parser::Statement::Statement(statement) = &ast[0] else {
+        return Err(NotSupported::Update.into());
+    };
+    let SqlStatement::Update {
+        table,
+        assignments,
+        selection,
+        ..
+    } = statement.as_ref()
+    else {
+        return Err(NotSupported::Update.into());
+    };
+
+    let where_clause = selection
+        .as_ref()
+        .map(|selection| format!(" WHERE {selection}"))
+        .unwrap_or_default();
+
+    let delete_plan = create_logical_plan(&format!("DELETE FROM {table}{where_clause}"))?;
+    let delete_schema = delete_plan.schema();
+
+    let mut set_expressions = HashMap::new();
+    for assignment in assignments {
+        let column_name = assignment
+            .id
+            .last()
+            .map(|ident| ident.value.as_str())
+            .unwrap_or_default();
+        delete_schema
+            .field_with_unqualified_name(column_name)
+            .map_err(ParadeError::DataFusion)?;
+        set_expressions.insert(column_name, assignment.value.to_string());
+    }
+
+    let select_list = delete_schema
+        .fields()
+        .iter()
+        .map(|field| match set_expressions.get(field.name().as_str()) {
+            Some(set_expression) => format!("{set_expression} AS {}", field.name()),
+            None => field.name().clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    Spi::run("DROP TABLE IF EXISTS pg_temp._paradedb_update_staging")?;
+    Spi::run(&format!(
+        "CREATE TEMP TABLE _paradedb_update_staging AS SELECT {select_list} FROM {table}{where_clause}"
+    ))?;
+
+    let result = delete(rtable, query_desc.clone(), delete_plan).and_then(|_| {
+        // `Spi::run` re-enters `executor_run` for this nested INSERT, whose own commit-on-entry
+        // check would otherwise commit the delete above alone, before the insert has even run.
+        // Suppress it here so the single `commit_writer` call below covers delete+insert together.
+        let _guard = SkipAutoCommitGuard::new();
+        Spi::run(&format!(
+            "INSERT INTO {table} SELECT * FROM _paradedb_update_staging"
+        ))
+        .map_err(ParadeError::from)
+    });
+
+    // Only drop the staging table once its rows have actually landed back in `table`; on
+    // failure it's left behind as the recoverable record of what the UPDATE would have
+    // produced, and the error below still propagates so this statement reports failure
+    // rather than silently leaving the delete as the only applied half of the UPDATE.
+    if result.is_ok() {
+        Spi::run("DROP TABLE _paradedb_update_staging")?;
+    }
+    result?;
+
+    if needs_commit()? {
+        task::block_on(commit_writer())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgrx::pg_schema]
+mod tests {
+    use pgrx::prelude::*;
+
+    // When the staged insert half of an UPDATE fails, the delete it was meant to replace
+    // has already run and the staging table is the only surviving record of the rows that
+    // should have been re-inserted -- `update` deliberately leaves it in place (see the
+    // comment above `update`) instead of dropping it, so a failed UPDATE is recoverable
+    // rather than silently lossy.
+    #[pg_test]
+    fn update_insert_failure_keeps_staging_table() {
+        Spi::run("CREATE FOREIGN TABLE update_insert_failure_t (id INT, name TEXT) SERVER parquet_server")
+            .expect("creating deltalake table");
+        Spi::run("INSERT INTO update_insert_failure_t VALUES (1, 'a'), (2, 'b')")
+            .expect("seeding rows");
+
+        // `name` is assigned an integer, which DataFusion can't coerce into the insert's
+        // text column -- the staged insert fails, while the preceding delete has already run.
+        let _ = Spi::run("UPDATE update_insert_failure_t SET name = 12345 WHERE id = 1");
+
+        let staging_exists = Spi::get_one::<bool>(
+            "SELECT EXISTS (SELECT 1 FROM pg_tables WHERE tablename = '_paradedb_update_staging')",
+        )
+        .expect("checking for the staging table")
+        .unwrap_or(false);
+        assert!(
+            staging_exists,
+            "staging table should survive a failed UPDATE insert"
+        );
+    }
+}
+
 #[inline]
 fn create_logical_plan(query: &str) -> Result<LogicalPlan, ParadeError> {
     let dialect = PostgreSqlDialect {};
@@ -0,0 +1,30 @@
+use thiserror::Error;
+
+/// Errors surfaced by `SearchState` and the rest of the index-scan layer. These are
+/// returned rather than panicked so a malformed query or schema drift produces a
+/// catchable SQL error instead of unwinding across the Postgres FFI boundary.
+//
+// TODO: the index AM's scan code that calls into `SearchState` isn't part of this checkout,
+// so it still needs to translate a returned `SearchError` into an `ereport`-style Postgres
+// error at the AM boundary (e.g. via `pgrx::error!` or `PgSqlErrorCode`) -- right now nothing
+// actually surfaces this enum to the user.
+#[derive(Error, Debug)]
+pub enum SearchError {
+    #[error("could not parse query: {0}")]
+    QueryParse(String),
+
+    #[error("field '{0}' not found in schema")]
+    FieldNotFound(String),
+
+    #[error("value for key field '{0}' not found in doc")]
+    KeyFieldValueNotFound(String),
+
+    #[error("key field '{0}' is not an i64")]
+    KeyFieldNotI64(String),
+
+    #[error(transparent)]
+    Tantivy(#[from] tantivy::TantivyError),
+
+    #[error("could not create {0}-thread search executor: {1}")]
+    ExecutorInit(usize, String),
+}
@@ -0,0 +1,140 @@
+use once_cell::sync::Lazy;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use tantivy::{query::Query, DocAddress};
+
+use super::score::SearchIndexScore;
+use crate::schema::{SearchConfig, SearchIndexSchema};
+
+/// The Tantivy index's own commit opstamp, read fresh from its on-disk metadata. Unlike an
+/// in-process counter, this is visible to every Postgres backend that has the index open --
+/// a commit from backend A is immediately reflected in the opstamp backend B reads, so a
+/// cache entry keyed on it is never served past a commit it didn't observe, no matter which
+/// process made that commit.
+pub type IndexGeneration = tantivy::Opstamp;
+
+/// Identifies the slice of a `SearchConfig` that determines its result set: the target
+/// index, the query itself, the key field, and the schema it was parsed against.
+/// `limit_rows` and `offset_rows` are deliberately excluded so that paginated queries
+/// against the same predicate share one cache entry keyed on the unpaginated superset of
+/// results. The index's relfilenode is included because two different bm25 indexes can
+/// otherwise hash identically (same query text, structurally identical schema) despite
+/// their `DocAddress`es not being interchangeable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConfigFingerprint(u64);
+
+/// The cached outcome of parsing and executing a `SearchConfig`'s query: the parsed
+/// query (so repeat lookups skip `into_tantivy_query`) and the unpaginated top-docs.
+/// `top_docs` is reference-counted so a cache hit is a pointer bump rather than a clone of
+/// the whole result vector, even while holding `CACHE`'s lock.
+#[derive(Clone)]
+pub struct QueryValue {
+    pub query: Box<dyn Query>,
+    pub top_docs: Arc<Vec<(SearchIndexScore, DocAddress)>>,
+}
+
+#[allow(clippy::type_complexity)]
+static CACHE: Lazy<Mutex<HashMap<ConfigFingerprint, (QueryValue, IndexGeneration)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Returns the generation `index` is currently at, read from its on-disk metadata rather
+/// than a process-local counter. Postgres is process-per-connection, so a counter bumped
+/// only by whichever backend happens to call `IndexWriter::commit` would leave every other
+/// backend serving stale results forever; reading the opstamp straight from the index's
+/// meta file instead means any backend observes any other backend's commit.
+pub fn current_generation(index: &tantivy::Index) -> tantivy::Result<IndexGeneration> {
+    Ok(index.load_metas()?.opstamp)
+}
+
+/// Computes the fingerprint of the portion of `config` that affects its result set,
+/// scoped to `index_oid` so two different bm25 indexes never share a cache entry.
+pub fn fingerprint(
+    index_oid: pgrx::pg_sys::Oid,
+    config: &SearchConfig,
+    schema: &SearchIndexSchema,
+) -> ConfigFingerprint {
+    let mut hasher = DefaultHasher::new();
+    index_oid.hash(&mut hasher);
+    format!("{:?}", config.query).hash(&mut hasher);
+    schema.key_field().name.0.hash(&mut hasher);
+    format!("{:?}", schema.schema).hash(&mut hasher);
+    ConfigFingerprint(hasher.finish())
+}
+
+/// Looks up a cached `QueryValue` for `fingerprint`, returning `None` if there is no
+/// entry or the entry was stored at a generation other than `generation`.
+pub fn get(fingerprint: ConfigFingerprint, generation: IndexGeneration) -> Option<QueryValue> {
+    let cache = CACHE.lock().expect("search cache lock should not be poisoned");
+    cache.get(&fingerprint).and_then(|(value, cached_generation)| {
+        if *cached_generation == generation {
+            Some(value.clone())
+        } else {
+            None
+        }
+    })
+}
+
+/// Stores `value` for `fingerprint` at `generation`, overwriting any stale entry.
+pub fn put(fingerprint: ConfigFingerprint, generation: IndexGeneration, value: QueryValue) {
+    let mut cache = CACHE.lock().expect("search cache lock should not be poisoned");
+    cache.insert(fingerprint, (value, generation));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tantivy::query::AllQuery;
+    use tantivy::schema::{Schema, TEXT};
+    use tantivy::{doc, Index};
+
+    fn test_index() -> Index {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("body", TEXT);
+        Index::create_in_ram(schema_builder.build())
+    }
+
+    fn test_value() -> QueryValue {
+        QueryValue {
+            query: Box::new(AllQuery),
+            top_docs: Arc::new(Vec::new()),
+        }
+    }
+
+    #[test]
+    fn cache_hit_survives_a_no_op_reopen() {
+        let index = test_index();
+        let generation = current_generation(&index).expect("reading a fresh index's generation");
+        let fingerprint = ConfigFingerprint(1);
+        put(fingerprint, generation, test_value());
+
+        // Re-reading the generation without an intervening commit doesn't change the
+        // opstamp, so the entry stored above is still a hit.
+        let reread = current_generation(&index).expect("re-reading the same index's generation");
+        assert_eq!(generation, reread);
+        assert!(get(fingerprint, reread).is_some());
+    }
+
+    #[test]
+    fn cache_entry_goes_stale_after_a_commit() {
+        let index = test_index();
+        let generation = current_generation(&index).expect("reading a fresh index's generation");
+        let fingerprint = ConfigFingerprint(2);
+        put(fingerprint, generation, test_value());
+
+        let mut writer = index.writer(15_000_000).expect("creating an index writer");
+        let body = index.schema().get_field("body").expect("body field exists");
+        writer
+            .add_document(doc!(body => "hello"))
+            .expect("adding a document");
+        writer.commit().expect("committing the write");
+
+        // The commit above bumped the opstamp, so the entry stored at the pre-commit
+        // generation is no longer served -- a backend that only observed the old
+        // generation must re-run the query instead of getting pre-commit results.
+        let post_commit = current_generation(&index).expect("reading the post-commit generation");
+        assert_ne!(generation, post_commit);
+        assert!(get(fingerprint, post_commit).is_none());
+    }
+}
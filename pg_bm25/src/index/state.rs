@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use tantivy::collector::TopDocs;
 use tantivy::{
     query::{Query, QueryParser},
@@ -6,61 +7,258 @@ use tantivy::{
 };
 use tantivy::{DocId, Document, SegmentReader};
 
+use once_cell::sync::Lazy;
+
+use super::cache::{self, ConfigFingerprint, QueryValue};
 use super::score::SearchIndexScore;
 use super::SearchIndex;
+use crate::errors::SearchError;
 use crate::schema::{SearchConfig, SearchIndexSchema};
 
+// TODO: the index AM's scan code (`amrescan`/`amgettuple` and friends) still calls this
+// module's old signatures -- raw `.iterator` access instead of `SearchCursor`, and
+// `SearchState::new`/`.search()`/`.key_field_value(...)` as bare/panicking calls instead of
+// `Result`. That scan code isn't part of this checkout, so it can't be updated here; whoever
+// lands this also needs to update every AM/scan call site to the `Result`-returning,
+// `SearchCursor`-based API below, including translating `SearchError` into Postgres
+// `ereport`-style errors at the AM boundary per chunk0-5's original ask.
+
+/// An owned, resumable cursor over a search's results, paged in lazily as the scan
+/// advances rather than materialized all at once.
+///
+/// This replaces a raw `*mut IntoIter` that the Postgres index AM previously advanced
+/// across calls: a dangling or aliased pointer there is a use-after-free/leak hazard at
+/// the FFI boundary, and it cannot be reset, which `amrescan` needs to do for nested-loop
+/// joins. `SearchCursor` only buffers the current page; `SearchState::next_result` refills
+/// it from `search_range` on demand, so a scan that's satisfied by its first few rows never
+/// pays to collect and sort the rest of the matching set.
+pub struct SearchCursor {
+    buffer: Vec<(SearchIndexScore, DocAddress)>,
+    position: usize,
+    fetched: usize,
+    exhausted: bool,
+}
+
+impl SearchCursor {
+    fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            position: 0,
+            fetched: 0,
+            exhausted: false,
+        }
+    }
+
+    /// Returns the next already-buffered result without advancing the cursor, or `None` if
+    /// the current page is exhausted. A `None` here doesn't necessarily mean the scan is
+    /// over -- call `SearchState::next_result` to pull the next page and advance.
+    pub fn peek(&self) -> Option<&(SearchIndexScore, DocAddress)> {
+        self.buffer.get(self.position)
+    }
+
+    /// Resets the cursor to the first result, discarding any buffered page so the scan
+    /// re-fetches from the start (e.g. on `amrescan`). The re-fetch is a cache hit only when
+    /// the cached top-docs hold at least as many entries as the first page's end (or every
+    /// document in the index); a query with fewer matches than that runs its search again.
+    pub fn rewind(&mut self) {
+        self.buffer.clear();
+        self.position = 0;
+        self.fetched = 0;
+        self.exhausted = false;
+    }
+}
+
 pub struct SearchState {
     pub schema: SearchIndexSchema,
     pub query: Box<dyn Query>,
     pub parser: QueryParser,
     pub searcher: Searcher,
-    pub iterator: *mut std::vec::IntoIter<(SearchIndexScore, DocAddress)>,
+    pub iterator: Option<SearchCursor>,
     pub config: SearchConfig,
     pub key_field_name: String,
+    fingerprint: ConfigFingerprint,
+    generation: cache::IndexGeneration,
+    parallel_workers: Option<usize>,
 }
 
 impl SearchState {
-    pub fn new(search_index: &SearchIndex, config: &SearchConfig) -> Self {
+    pub fn new(search_index: &SearchIndex, config: &SearchConfig) -> Result<Self, SearchError> {
         let schema = search_index.schema.clone();
         let mut parser = search_index.query_parser();
-        let query = config
-            .query
-            .clone()
-            .into_tantivy_query(&schema, &mut parser)
-            .unwrap_or_else(|err| panic!("could not parse query: {err}"));
+        let searcher = search_index.searcher();
+        let fingerprint = cache::fingerprint(search_index.oid, config, &schema);
+        // Pinned once here, alongside the `Searcher` snapshot it describes, and reused for
+        // every `search_range` call this `SearchState` makes. Re-reading the live generation
+        // per-call would let a commit that lands after this snapshot was taken tag this
+        // snapshot's (pre-commit) results with a post-commit generation, serving them to any
+        // other backend that queries the same fingerprint at that generation afterwards.
+        let generation = cache::current_generation(searcher.index())?;
+
+        // A cache hit at the current generation means an identical (modulo limit/offset)
+        // query has already been parsed and run against this exact state of the index, so
+        // we can skip `into_tantivy_query` entirely and let `search()` reuse its top-docs.
+        let query = match cache::get(fingerprint, generation) {
+            Some(cached) => cached.query,
+            None => config
+                .query
+                .clone()
+                .into_tantivy_query(&schema, &mut parser)
+                .map_err(|err| SearchError::QueryParse(err.to_string()))?,
+        };
         let key_field_name = schema.key_field().name.0;
-        SearchState {
+        // `SearchConfig` doesn't carry a parallelism knob yet -- call `with_parallel_workers`
+        // on the returned `SearchState` once one is added, rather than reaching for a field
+        // that doesn't exist on the struct.
+        Ok(SearchState {
             schema,
             query,
             parser,
             config: config.clone(),
-            searcher: search_index.searcher(),
-            iterator: std::ptr::null_mut(),
+            searcher,
+            iterator: None,
             key_field_name,
+            fingerprint,
+            generation,
+            parallel_workers: None,
+        })
+    }
+
+    /// Opts this scan into fanning `search()`'s `TopDocs` collection across `num_threads`
+    /// worker threads instead of running it serially on the calling backend. Left unset
+    /// (the default), `search()` runs on a single thread, matching the historical
+    /// behavior under Postgres's process-per-connection model.
+    pub fn with_parallel_workers(mut self, num_threads: usize) -> Self {
+        self.parallel_workers = Some(num_threads);
+        self
+    }
+
+    /// How many results `next_result` pulls from `search_range` at a time. Keeps an early
+    /// LIMIT, or a scan the AM abandons after a few rows, from paying to materialize the
+    /// whole result set up front.
+    const CURSOR_PAGE_SIZE: usize = 100;
+
+    /// Returns the scan's next result, paging in a new batch via `search_range` once the
+    /// current one is exhausted, or `None` once every row within the scan's configured
+    /// offset/limit has been returned. The index AM can call this on every scan iteration.
+    pub fn next_result(&mut self) -> Result<Option<(SearchIndexScore, DocAddress)>, SearchError> {
+        if self.iterator.is_none() {
+            self.iterator = Some(SearchCursor::new());
+        }
+
+        loop {
+            let cursor = self.iterator.as_mut().expect("cursor was just initialized");
+            if let Some(result) = cursor.buffer.get(cursor.position).cloned() {
+                cursor.position += 1;
+                return Ok(Some(result));
+            }
+            if cursor.exhausted {
+                return Ok(None);
+            }
+
+            let base_offset = self.config.offset_rows.unwrap_or(0);
+            let total_limit = self.config.limit_rows.unwrap_or(usize::MAX);
+            let fetched = cursor.fetched;
+            if fetched >= total_limit {
+                cursor.exhausted = true;
+                continue;
+            }
+
+            let page_limit = Self::CURSOR_PAGE_SIZE.min(total_limit - fetched);
+            let page = self.search_range(base_offset + fetched, page_limit)?;
+
+            let cursor = self.iterator.as_mut().expect("cursor was just initialized");
+            if page.is_empty() {
+                cursor.exhausted = true;
+                continue;
+            }
+            cursor.fetched += page.len();
+            cursor.buffer = page;
+            cursor.position = 0;
+        }
+    }
+
+    /// Resets the scan's cursor to its first result, discarding any buffered page. Used by
+    /// the index AM to implement `amrescan`.
+    pub fn rewind_cursor(&mut self) -> Result<(), SearchError> {
+        if self.iterator.is_none() {
+            self.iterator = Some(SearchCursor::new());
         }
+        self.iterator
+            .as_mut()
+            .expect("cursor was just initialized")
+            .rewind();
+        Ok(())
     }
 
-    pub fn key_field_value(&mut self, doc_address: DocAddress) -> i64 {
-        let retrieved_doc = self.searcher.doc(doc_address).expect("could not find doc");
+    pub fn key_field_value(&mut self, doc_address: DocAddress) -> Result<i64, SearchError> {
+        let retrieved_doc = self.searcher.doc(doc_address)?;
 
         let key_field = self
             .schema
             .schema
             .get_field(&self.key_field_name)
-            .expect("field '{key_field_name}' not found in schema");
-
-        if let tantivy::schema::Value::I64(key_field_value) =
-            retrieved_doc.get_first(key_field).unwrap_or_else(|| {
-                panic!(
-                    "value for key_field '{}' not found in doc",
-                    &self.key_field_name,
-                )
-            })
-        {
-            *key_field_value
-        } else {
-            panic!("error unwrapping ctid value")
+            .map_err(|_| SearchError::FieldNotFound(self.key_field_name.clone()))?;
+
+        match retrieved_doc.get_first(key_field) {
+            Some(tantivy::schema::Value::I64(key_field_value)) => Ok(*key_field_value),
+            Some(_) => Err(SearchError::KeyFieldNotI64(self.key_field_name.clone())),
+            None => Err(SearchError::KeyFieldValueNotFound(
+                self.key_field_name.clone(),
+            )),
+        }
+    }
+
+    /// Returns the `num_threads`-wide `Executor` `search()` fans its per-segment collection
+    /// across, building one the first time this exact thread count is requested and reusing
+    /// it for every later call with the same count, rather than spinning up a fresh worker
+    /// pool on every cache-miss search.
+    fn parallel_executor(&self, num_threads: usize) -> Result<Arc<tantivy::Executor>, SearchError> {
+        #[allow(clippy::type_complexity)]
+        static PARALLEL_EXECUTORS: Lazy<Mutex<HashMap<usize, Arc<tantivy::Executor>>>> =
+            Lazy::new(|| Mutex::new(HashMap::new()));
+
+        let mut executors = PARALLEL_EXECUTORS
+            .lock()
+            .expect("parallel executor cache lock should not be poisoned");
+        match executors.entry(num_threads) {
+            std::collections::hash_map::Entry::Occupied(entry) => Ok(entry.get().clone()),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                let executor = tantivy::Executor::multi_thread(num_threads, "pg_bm25-search-")
+                    .map_err(|err| SearchError::ExecutorInit(num_threads, err.to_string()))?;
+                Ok(entry.insert(Arc::new(executor)).clone())
+            }
+        }
+    }
+
+    /// Returns the `Executor` that `search()` fans its per-segment collection across.
+    /// Defaults to a single-thread executor so behavior is unchanged under Postgres's
+    /// process-per-connection model; call `with_parallel_workers` to fan the `TopDocs`
+    /// collector across a worker pool of that exact size instead, merging per-segment
+    /// top-K heaps on the calling backend.
+    fn executor(&self) -> Result<Arc<tantivy::Executor>, SearchError> {
+        static SERIAL_EXECUTOR: Lazy<Arc<tantivy::Executor>> =
+            Lazy::new(|| Arc::new(tantivy::Executor::single_thread()));
+        match self.parallel_workers {
+            Some(num_threads) if num_threads > 1 => self.parallel_executor(num_threads),
+            _ => Ok(SERIAL_EXECUTOR.clone()),
+        }
+    }
+
+    /// Confirms the key field is present in the schema and indexed as a fast i64 field, the
+    /// precondition `search_range()`'s `tweak_score` closure relies on to read it directly off a
+    /// `SegmentReader` instead of round-tripping through `self.searcher.doc`. Checked up
+    /// front so schema drift produces a catchable `SearchError` rather than a panic once
+    /// `search_range()` is already inside Tantivy's collector callback.
+    fn validate_key_field_is_fast_i64(&self) -> Result<(), SearchError> {
+        let field = self
+            .schema
+            .schema
+            .get_field(&self.key_field_name)
+            .map_err(|_| SearchError::FieldNotFound(self.key_field_name.clone()))?;
+
+        match self.schema.schema.get_field_entry(field).field_type() {
+            tantivy::schema::FieldType::I64(options) if options.is_fast() => Ok(()),
+            _ => Err(SearchError::KeyFieldNotI64(self.key_field_name.clone())),
         }
     }
 
@@ -68,53 +266,92 @@ impl SearchState {
     /// index access methods, this may return deleted rows until a VACUUM. If you need to scan
     /// the Tantivy index without a Postgres deduplication, you should use the `search_dedup`
     /// method instead.
-    pub fn search(&mut self) -> Vec<(SearchIndexScore, DocAddress)> {
-        // Extract limit and offset from the query config or set defaults.
-        let limit = self.config.limit_rows.unwrap_or_else(|| {
-            // We use unwrap_or_else here so this block doesn't run unless
-            // we actually need the default value. This is important, because there can
-            // be some cost to Tantivy API calls.
-            let num_docs = self.searcher.num_docs() as usize;
-            if num_docs > 0 {
-                num_docs // The collector will panic if it's passed a limit of 0.
-            } else {
-                1 // Since there's no docs to return anyways, just use 1.
+    pub fn search(&mut self) -> Result<Vec<(SearchIndexScore, DocAddress)>, SearchError> {
+        let num_docs = self.searcher.num_docs() as usize;
+        let offset = self.config.offset_rows.unwrap_or(0);
+        let limit = self.config.limit_rows.unwrap_or(num_docs.max(1));
+        self.search_range(offset, limit)
+    }
+
+    /// Searches for the `limit` matching documents starting at `offset`, used directly by
+    /// `search` for the scan's full configured window and by `next_result` to page a cursor
+    /// through that same window a little at a time.
+    fn search_range(
+        &mut self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<(SearchIndexScore, DocAddress)>, SearchError> {
+        self.validate_key_field_is_fast_i64()?;
+
+        let num_docs = self.searcher.num_docs() as usize;
+
+        // The size of the cached, unpaginated superset: we collect a window a few pages
+        // past `offset + limit` (rather than the entire corpus) so that neighboring pages
+        // of the same predicate still share a cache entry, without forcing every query --
+        // including ones with a small `LIMIT` -- to sort and clone every matching document.
+        const CACHE_WINDOW_MULTIPLIER: usize = 4;
+        let cache_limit = offset
+            .saturating_add(limit)
+            .saturating_mul(CACHE_WINDOW_MULTIPLIER)
+            .clamp(1, num_docs.max(1));
+
+        let top_docs = match cache::get(self.fingerprint, self.generation) {
+            Some(cached) if cached.top_docs.len() >= (offset + limit).min(num_docs) => {
+                cached.top_docs
             }
-        });
+            _ => {
+                let key_field_name = self.key_field_name.clone();
+                let top_docs_by_custom_score = TopDocs::with_limit(cache_limit).tweak_score(
+                    // tweak_score expects a function that will return a function. A little
+                    // unusual for Rust, but not too much of a problem as long as you don't
+                    // need to reference many variables outside the function scope.
+                    move |segment_reader: &SegmentReader| {
+                        let key_field_reader = segment_reader
+                            .fast_fields()
+                            .i64(&key_field_name)
+                            .expect("key field fast-ness was already validated by search_range()")
+                            .first_or_default_col(0);
 
-        let offset = self.config.offset_rows.unwrap_or(0);
-        let key_field_name = self.key_field_name.clone();
-        let top_docs_by_custom_score = TopDocs::with_limit(limit).and_offset(offset).tweak_score(
-            // tweak_score expects a function that will return a function. A little unusual for
-            // Rust, but not too much of a problem as long as you don't need to reference
-            // many variables outside the function scope.
-            move |segment_reader: &SegmentReader| {
-                let key_field_reader = segment_reader
-                    .fast_fields()
-                    .i64(&key_field_name)
-                    .unwrap_or_else(|err| {
-                        panic!("key field {} is not a u64: {err:?}", &key_field_name)
-                    })
-                    .first_or_default_col(0);
-
-                move |doc: DocId, original_score: Score| SearchIndexScore {
-                    bm25: original_score,
-                    key: key_field_reader.get_val(doc),
-                }
-            },
-        );
-
-        self.searcher
-            .search(&self.query, &top_docs_by_custom_score)
-            .expect("failed to search")
+                        move |doc: DocId, original_score: Score| SearchIndexScore {
+                            bm25: original_score,
+                            key: key_field_reader.get_val(doc),
+                        }
+                    },
+                );
+
+                let executor = self.executor()?;
+                let top_docs = self.searcher.search_with_executor(
+                    &self.query,
+                    &top_docs_by_custom_score,
+                    executor,
+                    tantivy::query::EnableScoring::enabled_from_searcher(&self.searcher),
+                )?;
+
+                let top_docs = Arc::new(top_docs);
+                cache::put(
+                    self.fingerprint,
+                    self.generation,
+                    QueryValue {
+                        query: self.query.clone(),
+                        top_docs: top_docs.clone(),
+                    },
+                );
+
+                top_docs
+            }
+        };
+
+        Ok(top_docs.iter().cloned().skip(offset).take(limit).collect())
     }
 
     /// A search method that deduplicates results based on key field. This is important for
     /// searches into the Tantivy index outside of Postgres index access methods. Postgres will
     /// filter out stale rows when using the index scan, but when scanning Tantivy directly,
     /// we risk returning deleted documents if a VACUUM hasn't been performed yet.
-    pub fn search_dedup(&mut self) -> impl Iterator<Item = (SearchIndexScore, DocAddress)> {
-        let search_results = self.search();
+    pub fn search_dedup(
+        &mut self,
+    ) -> Result<impl Iterator<Item = (SearchIndexScore, DocAddress)>, SearchError> {
+        let search_results = self.search()?;
         let mut dedup_map: HashMap<i64, (SearchIndexScore, DocAddress)> = HashMap::new();
         let mut order_vec: Vec<i64> = Vec::new();
 
@@ -130,9 +367,9 @@ impl SearchState {
             }
         }
 
-        order_vec
+        Ok(order_vec
             .into_iter()
-            .filter_map(move |key| dedup_map.remove(&key))
+            .filter_map(move |key| dedup_map.remove(&key)))
     }
 
     #[allow(unused)]